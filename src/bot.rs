@@ -1,16 +1,41 @@
+use std::time::Duration;
+
 use futures_util::stream::StreamExt;
 use tokio_tungstenite::tungstenite::Message;
 
+use crate::candle_series::CandleSeries;
 use crate::candlestick::Candlestick;
 use crate::expertoption::ExpertOption;
-use crate::message::Message as BotMessage;
+use crate::message::{Message as BotMessage, ParseError};
+use crate::subscription::{Channel, Command};
 use crate::trade::Trade;
+use crate::tradingview::{Quote, TradingViewError};
 use crate::trend::Trend;
 
+/// How often to send a heartbeat ping, matching the ~240s cadence exchanges
+/// like Binance expect to keep a feed alive.
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(240);
+
+/// How many recent candles to retain for indicator confirmation.
+const SERIES_CAPACITY: usize = 50;
+
+/// The moving-average lookback used to confirm trend entries.
+const SMA_PERIOD: usize = 10;
+
+/// Why `Bot::run` returned, so the caller knows whether to reconnect.
+pub enum RunOutcome {
+    /// The server closed the connection cleanly.
+    Closed,
+    /// The underlying stream errored.
+    Error,
+}
+
 pub struct Bot {
     ws: ExpertOption,
     trend: Trend,
     trades: Vec<Trade>,
+    heartbeat_interval: Duration,
+    series: CandleSeries,
 }
 
 impl Bot {
@@ -19,60 +44,174 @@ impl Bot {
             ws,
             trend: Trend::Unknown,
             trades: Vec::new(),
+            heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL,
+            series: CandleSeries::new(SERIES_CAPACITY),
+        }
+    }
+
+    /// Override the heartbeat ping interval (defaults to 240s).
+    pub fn set_heartbeat_interval(&mut self, interval: Duration) {
+        self.heartbeat_interval = interval;
+    }
+
+    /// The channels this bot's socket is currently subscribed to, for replay
+    /// after a reconnect.
+    pub fn subscriptions(&self) -> Vec<Channel> {
+        self.ws.active_subscriptions()
+    }
+
+    /// Add channels to this socket's active subscription set at runtime, so
+    /// a caller can bring up another candle feed without reconnecting.
+    pub async fn subscribe(
+        &mut self,
+        channels: &[Channel],
+    ) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        self.ws.subscribe(channels).await
+    }
+
+    /// Drop channels from this socket's active subscription set at runtime.
+    pub async fn unsubscribe(
+        &mut self,
+        channels: &[Channel],
+    ) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        self.ws.unsubscribe(channels).await
+    }
+
+    /// Re-subscribe to every active channel, used to force a fresh order
+    /// book after a checksum mismatch.
+    async fn resubscribe(&mut self) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        let channels = self.ws.active_subscriptions();
+        if channels.is_empty() {
+            return Ok(());
         }
+        self.ws.subscribe(&channels).await
     }
 
     pub async fn run(
         &mut self,
         mut read: impl StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
-    ) {
-        while let Some(msg) = read.next().await {
-            match msg {
-                Ok(Message::Text(text)) => {
-                    let msg = BotMessage::from_text(&text);
-                    self.handle_message(msg).await;
+    ) -> RunOutcome {
+        let mut heartbeat = tokio::time::interval(self.heartbeat_interval);
+        heartbeat.tick().await; // the first tick fires immediately; skip it
+
+        loop {
+            tokio::select! {
+                _ = heartbeat.tick() => {
+                    if self.ws.ping().await.is_err() {
+                        eprintln!("Failed to send heartbeat ping");
+                        return RunOutcome::Error;
+                    }
                 }
-                Ok(Message::Close(_)) => {
-                    print!("Server closed the connection\n");
-                    break;
+                msg = read.next() => {
+                    match msg {
+                        Some(Ok(Message::Text(text))) => {
+                            if let Ok(command) = serde_json::from_str::<Command>(&text) {
+                                if self.handle_command(command).await.is_err() {
+                                    eprintln!("Failed to apply subscription command");
+                                    return RunOutcome::Error;
+                                }
+                                continue;
+                            }
+                            match BotMessage::from_text(&text) {
+                                Ok(msg) => self.handle_message(msg).await,
+                                Err(e @ ParseError::ChecksumMismatch { .. }) => {
+                                    eprintln!("{}, forcing resubscribe", e);
+                                    if self.resubscribe().await.is_err() {
+                                        eprintln!("Failed to resubscribe");
+                                        return RunOutcome::Error;
+                                    }
+                                }
+                                Err(e) => eprintln!("Dropping malformed frame: {}", e),
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            if self.ws.pong(payload).await.is_err() {
+                                eprintln!("Failed to send pong");
+                                return RunOutcome::Error;
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) | None => {
+                            print!("Server closed the connection\n");
+                            return RunOutcome::Closed;
+                        }
+                        Some(Err(e)) => {
+                            print!("Error: {}\n", e);
+                            return RunOutcome::Error;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Consume a stream of TradingView quotes as an alternative to the
+    /// ExpertOption feed, driving the same trend/trade logic.
+    pub async fn run_quotes(
+        &mut self,
+        mut quotes: impl StreamExt<Item = Result<Quote, TradingViewError>> + Unpin,
+    ) -> RunOutcome {
+        while let Some(quote) = quotes.next().await {
+            match quote {
+                Ok(quote) => {
+                    if let Some(candlestick) = Candlestick::from_quote(&quote) {
+                        self.handle_candlestick(candlestick).await;
+                    }
                 }
                 Err(e) => {
-                    print!("Error: {}\n", e);
-                    break;
+                    print!("TradingView stream error: {}\n", e);
+                    return RunOutcome::Error;
                 }
-                _ => {}
             }
         }
+        RunOutcome::Closed
+    }
+
+    /// Apply a subscription control frame sent over the same socket.
+    async fn handle_command(
+        &mut self,
+        command: Command,
+    ) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        match command {
+            Command::Subscribe(channels) => self.ws.subscribe(&channels).await,
+            Command::Unsubscribe(channels) => self.ws.unsubscribe(&channels).await,
+        }
     }
 
     async fn handle_message(&mut self, msg: BotMessage) {
         match msg {
-            BotMessage::Candles(candles) => {
-                let candlestick = Candlestick::from_candles(&candles);
-                self.trend = candlestick.analyze_trend();
-                self.execute_trades(&candlestick).await;
+            BotMessage::Candles(candle) => {
+                let candlestick = Candlestick::from_candle(candle);
+                self.handle_candlestick(candlestick).await;
             }
-            _ => {}
+            BotMessage::Trade { .. } | BotMessage::OrderBook { .. } | BotMessage::Bbo { .. } => {}
         }
     }
 
-    async fn execute_trades(&mut self, candlestick: &Candlestick) {
-        match self.trend {
-            Trend::Up => {
-                if candlestick.has_long_tail() {
-                    let trade = Trade::call(candlestick.close);
-                    self.trades.push(trade.clone());
-                    self.ws.send_trade(&trade).await;
-                }
-            }
-            Trend::Down => {
-                if candlestick.has_long_head() {
-                    let trade = Trade::put(candlestick.close);
-                    self.trades.push(trade.clone());
-                    self.ws.send_trade(&trade).await;
-                }
-            }
-            _ => {}
-        }
+    async fn handle_candlestick(&mut self, candlestick: Candlestick) {
+        self.trend = candlestick.analyze_trend();
+        self.series.push(candlestick);
+        self.execute_trades().await;
+    }
+
+    /// Gate entries on multi-candle indicator confirmation (an engulfing
+    /// pattern plus the close being on the right side of the SMA) instead
+    /// of a single candle's tail/head.
+    async fn execute_trades(&mut self) {
+        let Some(close) = self.series.latest().map(|c| c.close) else {
+            return;
+        };
+        let Some(sma) = self.series.sma(SMA_PERIOD) else {
+            return;
+        };
+
+        let trade = match self.trend {
+            Trend::Up if self.series.is_bullish_engulfing() && close > sma => Trade::call(close),
+            Trend::Down if self.series.is_bearish_engulfing() && close < sma => Trade::put(close),
+            _ => return,
+        };
+
+        self.trades.push(trade.clone());
+        self.ws.send_trade(&trade).await;
     }
 }
@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+
+use rust_decimal::Decimal;
+
+use crate::candlestick::Candlestick;
+use crate::price::Price;
+
+/// A rolling window of the most recent candles, so indicators that need
+/// more than one candle's history (moving averages, multi-candle patterns)
+/// have something to look back at.
+pub struct CandleSeries {
+    capacity: usize,
+    candles: VecDeque<Candlestick>,
+}
+
+impl CandleSeries {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            candles: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Push the newest candle, evicting the oldest once at capacity.
+    pub fn push(&mut self, candlestick: Candlestick) {
+        if self.candles.len() == self.capacity {
+            self.candles.pop_front();
+        }
+        self.candles.push_back(candlestick);
+    }
+
+    pub fn len(&self) -> usize {
+        self.candles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candles.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<&Candlestick> {
+        self.candles.back()
+    }
+
+    /// Simple moving average of the close over the last `period` candles.
+    pub fn sma(&self, period: usize) -> Option<Price> {
+        if period == 0 || self.candles.len() < period {
+            return None;
+        }
+        let sum: Price = self
+            .candles
+            .iter()
+            .rev()
+            .take(period)
+            .map(|c| c.close)
+            .sum();
+        Some(sum / Decimal::from(period as u64))
+    }
+
+    /// Exponential moving average of the close over the last `period`
+    /// candles, seeded with the SMA of that same window.
+    pub fn ema(&self, period: usize) -> Option<Price> {
+        if period == 0 || self.candles.len() < period {
+            return None;
+        }
+        let window: Vec<Price> = self
+            .candles
+            .iter()
+            .rev()
+            .take(period)
+            .map(|c| c.close)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect();
+
+        let smoothing = Decimal::from(2) / Decimal::from((period + 1) as u64);
+        let seed: Price = window.iter().sum::<Price>() / Decimal::from(period as u64);
+        let mut ema = seed;
+        for close in &window[1..] {
+            ema = (close - ema) * smoothing + ema;
+        }
+        Some(ema)
+    }
+
+    /// The highest high over the last `period` candles.
+    pub fn recent_high(&self, period: usize) -> Option<Price> {
+        self.candles
+            .iter()
+            .rev()
+            .take(period)
+            .map(|c| c.high)
+            .max()
+    }
+
+    /// The lowest low over the last `period` candles.
+    pub fn recent_low(&self, period: usize) -> Option<Price> {
+        self.candles.iter().rev().take(period).map(|c| c.low).min()
+    }
+
+    /// Whether the last two candles form a bullish engulfing pattern: a
+    /// bearish candle followed by a bullish candle whose body fully covers
+    /// the previous one.
+    pub fn is_bullish_engulfing(&self) -> bool {
+        let Some((previous, current)) = self.last_two() else {
+            return false;
+        };
+        previous.is_bearish()
+            && current.is_bullish()
+            && current.open <= previous.close
+            && current.close >= previous.open
+    }
+
+    /// Whether the last two candles form a bearish engulfing pattern: a
+    /// bullish candle followed by a bearish candle whose body fully covers
+    /// the previous one.
+    pub fn is_bearish_engulfing(&self) -> bool {
+        let Some((previous, current)) = self.last_two() else {
+            return false;
+        };
+        previous.is_bullish()
+            && current.is_bearish()
+            && current.open >= previous.close
+            && current.close <= previous.open
+    }
+
+    fn last_two(&self) -> Option<(&Candlestick, &Candlestick)> {
+        if self.candles.len() < 2 {
+            return None;
+        }
+        let mut iter = self.candles.iter().rev();
+        let current = iter.next()?;
+        let previous = iter.next()?;
+        Some((previous, current))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle_with_close(close: i64) -> Candlestick {
+        Candlestick {
+            open: Decimal::from(close),
+            close: Decimal::from(close),
+            high: Decimal::from(close),
+            low: Decimal::from(close),
+            volume: 0.0,
+            open_time: 0,
+            close_time: 0,
+        }
+    }
+
+    #[test]
+    fn ema_seeds_with_the_window_sma_not_the_oldest_close() {
+        let mut series = CandleSeries::new(3);
+        for close in [10, 20, 30] {
+            series.push(candle_with_close(close));
+        }
+
+        // seed = sma(3) = 20, smoothing = 2/4 = 0.5:
+        // ema = (20-20)*0.5+20 = 20, then (30-20)*0.5+20 = 25.
+        assert_eq!(series.ema(3), Some(Decimal::from(25)));
+    }
+}
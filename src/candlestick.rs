@@ -1,22 +1,65 @@
+use crate::price::Price;
+use crate::tradingview::Quote;
 use crate::trend::Trend;
 
+/// The raw OHLCV shape a single candle arrives in, following Binance's
+/// `KlineEvent` layout.
+pub struct CandleData {
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: f64,
+    pub open_time: u128,
+    pub close_time: u128,
+}
+
 pub struct Candlestick {
-    pub open: f64,
-    pub close: f64,
-    pub high: f64,
-    pub low: f64,
+    pub open: Price,
+    pub close: Price,
+    pub high: Price,
+    pub low: Price,
+    pub volume: f64,
+    pub open_time: u128,
+    pub close_time: u128,
 }
 
 impl Candlestick {
-    pub fn from_candles(candles: &[f64]) -> Self {
+    pub fn from_candle(data: CandleData) -> Self {
         Self {
-            open: candles[0],
-            close: candles[1],
-            high: candles[2],
-            low: candles[3],
+            open: data.open,
+            close: data.close,
+            high: data.high,
+            low: data.low,
+            volume: data.volume,
+            open_time: data.open_time,
+            close_time: data.close_time,
         }
     }
 
+    /// Build a candlestick from a single TradingView last-price quote, so the
+    /// same trend/trade logic can drive off either feed. TradingView reports
+    /// each quote's change from the previous one in `ch`, so that's used as
+    /// this candle's open relative to `lp`, giving `analyze_trend` a real
+    /// per-update signal instead of a flat open/close.
+    pub fn from_quote(quote: &Quote) -> Option<Self> {
+        let lp = quote.lp?;
+        let change = quote
+            .ch
+            .and_then(|ch| Price::try_from(ch).ok())
+            .unwrap_or(Price::ZERO);
+        let open = lp - change;
+        Some(Self {
+            open,
+            close: lp,
+            high: open.max(lp),
+            low: open.min(lp),
+            volume: quote.volume.unwrap_or(0.0),
+            open_time: quote.timestamp,
+            close_time: quote.timestamp,
+        })
+    }
+
     pub fn analyze_trend(&self) -> Trend {
         if self.close > self.open {
             Trend::Up
@@ -27,11 +70,11 @@ impl Candlestick {
         }
     }
 
-    pub fn has_long_tail(&self) -> bool {
-        self.open - self.low > self.high - self.close
+    pub fn is_bullish(&self) -> bool {
+        self.close > self.open
     }
 
-    pub fn has_long_head(&self) -> bool {
-        self.high - self.close > self.open - self.low
+    pub fn is_bearish(&self) -> bool {
+        self.close < self.open
     }
 }
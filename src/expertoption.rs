@@ -1,6 +1,7 @@
 use futures_util::SinkExt;
 use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
 
+use crate::subscription::{self, Channel, Subscription};
 use crate::trade::Trade;
 
 pub struct ExpertOption {
@@ -8,6 +9,7 @@ pub struct ExpertOption {
         WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
         Message,
     >,
+    subscription: Subscription,
 }
 
 impl ExpertOption {
@@ -17,11 +19,55 @@ impl ExpertOption {
             Message,
         >,
     ) -> Self {
-        Self { write }
+        Self {
+            write,
+            subscription: Subscription::new(),
+        }
     }
 
     pub async fn send_trade(&mut self, trade: &Trade) {
         let msg = Message::Text(trade.to_json());
         self.write.send(msg).await.expect("Failed to send message");
     }
+
+    /// Subscribe to the given channels and remember them so a reconnect can
+    /// replay the active set. Returns the socket write error, if any, so a
+    /// dead connection can be routed through the reconnect loop instead of
+    /// panicking the process.
+    pub async fn subscribe(
+        &mut self,
+        channels: &[Channel],
+    ) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        self.subscription.add(channels);
+        let msg = Message::Text(subscription::to_frame("subscribe", channels));
+        self.write.send(msg).await
+    }
+
+    /// Unsubscribe from the given channels and drop them from the active set.
+    pub async fn unsubscribe(
+        &mut self,
+        channels: &[Channel],
+    ) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        self.subscription.remove(channels);
+        let msg = Message::Text(subscription::to_frame("unsubscribe", channels));
+        self.write.send(msg).await
+    }
+
+    /// The channels currently subscribed to, for replay after a reconnect.
+    pub fn active_subscriptions(&self) -> Vec<Channel> {
+        self.subscription.active()
+    }
+
+    /// Send a heartbeat ping frame.
+    pub async fn ping(&mut self) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        self.write.send(Message::Ping(Vec::new())).await
+    }
+
+    /// Reply to a server ping with a pong carrying the same payload.
+    pub async fn pong(
+        &mut self,
+        payload: Vec<u8>,
+    ) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        self.write.send(Message::Pong(payload)).await
+    }
 }
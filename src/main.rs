@@ -1,29 +1,88 @@
 use futures_util::stream::StreamExt;
 use std::env;
+use std::time::Duration;
 use tokio_tungstenite::connect_async;
 
 mod bot;
+mod candle_series;
 mod candlestick;
 mod expertoption;
 mod message;
+mod price;
+mod subscription;
 mod trade;
 pub mod tradingview;
 mod trend;
 
-use bot::Bot;
+use bot::{Bot, RunOutcome};
 use expertoption::ExpertOption;
+use subscription::Channel;
+
+/// Exponential backoff cap between reconnect attempts.
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(60);
 
 #[tokio::main]
 async fn main() {
     let url = env::var("EO_WEBSOCKET_URL").expect("EO_WEBSOCKET_URL must be set");
+    // When set, trend/trade decisions are driven off a TradingView quote
+    // stream instead of the ExpertOption feed; trades still execute over
+    // the ExpertOption socket.
+    let tv_feed = env::var("EO_TV_PAIR")
+        .ok()
+        .zip(env::var("EO_TV_MARKET").ok());
+
+    let mut subscriptions: Vec<Channel> = Vec::new();
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        let (ws_stream, _) = match connect_async(&url).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("Failed to connect: {} (retrying in {:?})", e, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                continue;
+            }
+        };
+        println!("Connected to the server");
+        backoff = Duration::from_secs(1);
 
-    let (ws_stream, _) = connect_async(&url).await.expect("Failed to connect");
-    println!("Connected to the server");
+        let (write, read) = ws_stream.split();
 
-    let (write, read) = ws_stream.split();
+        let mut ws = ExpertOption::new(write);
+        if !subscriptions.is_empty() {
+            if let Err(e) = ws.subscribe(&subscriptions).await {
+                eprintln!("Failed to resubscribe: {} (retrying in {:?})", e, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                continue;
+            }
+        }
 
-    let ws = ExpertOption::new(write);
-    let mut bot = Bot::new(ws);
+        let mut bot = Bot::new(ws);
+        let outcome = match &tv_feed {
+            Some((pair, market)) => match tradingview::quote_stream(pair, market).await {
+                Ok(quotes) => bot.run_quotes(quotes).await,
+                Err(e) => {
+                    eprintln!(
+                        "Failed to start TradingView feed: {} (retrying in {:?})",
+                        e, backoff
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                    continue;
+                }
+            },
+            None => bot.run(read).await,
+        };
 
-    bot.run(read).await;
+        match outcome {
+            RunOutcome::Closed | RunOutcome::Error => {
+                subscriptions = bot.subscriptions();
+                eprintln!("Reconnecting in {:?}", backoff);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+            }
+        }
+    }
 }
@@ -1,20 +1,318 @@
-use serde_json::Value;
+use serde::Deserialize;
+use std::fmt;
+
+use crate::candlestick::CandleData;
+use crate::price::{self, Price};
+
+/// Errors that can occur while decoding an incoming exchange frame.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The frame was not valid JSON.
+    InvalidJson(serde_json::Error),
+    /// The frame was valid JSON but was missing a field this action requires.
+    MissingField(&'static str),
+    /// The frame's `action` field did not match any handled variant.
+    UnknownAction(String),
+    /// An order book frame's checksum didn't match the recomputed one,
+    /// meaning the local book has drifted and should be resubscribed.
+    ChecksumMismatch { expected: i32, actual: i32 },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidJson(err) => write!(f, "invalid JSON: {}", err),
+            Self::MissingField(field) => write!(f, "missing field: {}", field),
+            Self::UnknownAction(action) => write!(f, "unknown action: {}", action),
+            Self::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "order book checksum mismatch: expected {}, computed {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The outer envelope shared by every incoming frame.
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    action: String,
+    message: Option<serde_json::Value>,
+}
+
+/// Which side of the book a trade executed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// A single price/size level in an order book. Size is kept as a `Price`
+/// rather than `f64` so its exact wire-format digits round-trip into the
+/// checksum string unchanged.
+pub type PriceLevel = (Price, Price);
 
 pub enum Message {
-    Candles(Vec<f64>),
-    Unknown,
+    Candles(CandleData),
+    Trade {
+        price: Price,
+        size: f64,
+        side: Side,
+        ts: u128,
+    },
+    OrderBook {
+        bids: Vec<PriceLevel>,
+        asks: Vec<PriceLevel>,
+        ts: u128,
+        checksum: i32,
+    },
+    Bbo {
+        bid: PriceLevel,
+        ask: PriceLevel,
+        ts: u128,
+    },
 }
 
 impl Message {
-    pub fn from_text(text: &str) -> Self {
-        let value: Value = serde_json::from_str(text).unwrap();
+    pub fn from_text(text: &str) -> Result<Self, ParseError> {
+        // Well-formed JSON that fails to fit the envelope has no usable
+        // `action`; only syntax/EOF failures count as invalid JSON.
+        let envelope: Envelope = serde_json::from_str(text).map_err(|err| {
+            if err.is_data() {
+                ParseError::MissingField("action")
+            } else {
+                ParseError::InvalidJson(err)
+            }
+        })?;
+        let payload = envelope.message.ok_or(ParseError::MissingField("message"))?;
+
+        match envelope.action.as_str() {
+            "candles" => Ok(Self::Candles(parse_candle(&payload)?)),
+            "trade" => parse_trade(&payload),
+            "order_book" => parse_order_book(&payload),
+            "bbo" => parse_bbo(&payload),
+            other => Err(ParseError::UnknownAction(other.to_string())),
+        }
+    }
+}
+
+/// Decode a `{"open":...,"high":...,"low":...,"close":...,"volume":...,
+/// "open_time":...,"close_time":...}` object into `CandleData`.
+fn parse_candle(payload: &serde_json::Value) -> Result<CandleData, ParseError> {
+    let open = price::parse_price(&payload["open"]).ok_or(ParseError::MissingField("open"))?;
+    let high = price::parse_price(&payload["high"]).ok_or(ParseError::MissingField("high"))?;
+    let low = price::parse_price(&payload["low"]).ok_or(ParseError::MissingField("low"))?;
+    let close = price::parse_price(&payload["close"]).ok_or(ParseError::MissingField("close"))?;
+    let volume = payload["volume"]
+        .as_f64()
+        .ok_or(ParseError::MissingField("volume"))?;
+    let open_time = payload["open_time"]
+        .as_u64()
+        .ok_or(ParseError::MissingField("open_time"))? as u128;
+    let close_time = payload["close_time"]
+        .as_u64()
+        .ok_or(ParseError::MissingField("close_time"))? as u128;
+
+    Ok(CandleData {
+        open,
+        high,
+        low,
+        close,
+        volume,
+        open_time,
+        close_time,
+    })
+}
+
+/// Decode a `{"price":...,"size":...,"side":"buy"|"sell","ts":...}` object
+/// into `Message::Trade`.
+fn parse_trade(payload: &serde_json::Value) -> Result<Message, ParseError> {
+    let price = price::parse_price(&payload["price"]).ok_or(ParseError::MissingField("price"))?;
+    let size = payload["size"]
+        .as_f64()
+        .ok_or(ParseError::MissingField("size"))?;
+    let side = match payload["side"].as_str() {
+        Some("buy") => Side::Buy,
+        Some("sell") => Side::Sell,
+        _ => return Err(ParseError::MissingField("side")),
+    };
+    let ts = payload["ts"].as_u64().ok_or(ParseError::MissingField("ts"))? as u128;
+
+    Ok(Message::Trade {
+        price,
+        size,
+        side,
+        ts,
+    })
+}
+
+/// Decode a `{"bids":[[price,size],...],"asks":[[price,size],...],
+/// "ts":...,"checksum":...}` object into `Message::OrderBook`, validating
+/// the OKX-style CRC32 checksum over the top 25 levels.
+fn parse_order_book(payload: &serde_json::Value) -> Result<Message, ParseError> {
+    let bids = parse_levels(&payload["bids"]).ok_or(ParseError::MissingField("bids"))?;
+    let asks = parse_levels(&payload["asks"]).ok_or(ParseError::MissingField("asks"))?;
+    let ts = payload["ts"].as_u64().ok_or(ParseError::MissingField("ts"))? as u128;
+    let checksum = payload["checksum"]
+        .as_i64()
+        .ok_or(ParseError::MissingField("checksum"))? as i32;
+
+    let actual = order_book_checksum(&bids, &asks);
+    if actual != checksum {
+        return Err(ParseError::ChecksumMismatch {
+            expected: checksum,
+            actual,
+        });
+    }
+
+    Ok(Message::OrderBook {
+        bids,
+        asks,
+        ts,
+        checksum,
+    })
+}
+
+/// Decode a `{"bid":[price,size],"ask":[price,size],"ts":...}` object into
+/// `Message::Bbo`.
+fn parse_bbo(payload: &serde_json::Value) -> Result<Message, ParseError> {
+    let bid = parse_level(&payload["bid"]).ok_or(ParseError::MissingField("bid"))?;
+    let ask = parse_level(&payload["ask"]).ok_or(ParseError::MissingField("ask"))?;
+    let ts = payload["ts"].as_u64().ok_or(ParseError::MissingField("ts"))? as u128;
+
+    Ok(Message::Bbo { bid, ask, ts })
+}
+
+fn parse_level(value: &serde_json::Value) -> Option<PriceLevel> {
+    let pair = value.as_array()?;
+    let price = price::parse_price(pair.first()?)?;
+    let size = price::parse_price(pair.get(1)?)?;
+    Some((price, size))
+}
+
+fn parse_levels(value: &serde_json::Value) -> Option<Vec<PriceLevel>> {
+    value.as_array()?.iter().map(parse_level).collect()
+}
+
+/// Build the OKX-style checksum string by interleaving the top 25 bid/ask
+/// `price:size` pairs, then CRC32 it and cast to a signed 32-bit integer to
+/// match the value exchanges send on the wire.
+fn order_book_checksum(bids: &[PriceLevel], asks: &[PriceLevel]) -> i32 {
+    const DEPTH: usize = 25;
+    let mut parts = Vec::with_capacity(DEPTH * 4);
+
+    for i in 0..DEPTH {
+        if let Some((price, size)) = bids.get(i) {
+            parts.push(price.to_string());
+            parts.push(size.to_string());
+        }
+        if let Some((price, size)) = asks.get(i) {
+            parts.push(price.to_string());
+            parts.push(size.to_string());
+        }
+    }
+
+    let checksum_string = parts.join(":");
+    crc32fast::hash(checksum_string.as_bytes()) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn level(price: &str, size: &str) -> PriceLevel {
+        (Price::from_str(price).unwrap(), Price::from_str(size).unwrap())
+    }
+
+    #[test]
+    fn order_book_checksum_matches_a_known_value() {
+        let bids = vec![level("100.1", "2.5"), level("100.0", "1.0")];
+        let asks = vec![level("100.2", "3.0"), level("100.3", "0.5")];
+
+        // Computed independently with zlib.crc32 over the same
+        // "100.1:2.5:100.2:3.0:100.0:1.0:100.3:0.5" string.
+        assert_eq!(order_book_checksum(&bids, &asks), -868527451);
+    }
+
+    #[test]
+    fn parse_level_preserves_the_wire_format_size() {
+        let value = serde_json::json!(["1.5", "10.00000000"]);
+        let (price, size) = parse_level(&value).expect("valid level");
+
+        assert_eq!(price.to_string(), "1.5");
+        assert_eq!(size.to_string(), "10.00000000");
+    }
+
+    #[test]
+    fn from_text_decodes_a_correct_order_book_frame() {
+        let text = r#"{"action":"order_book","message":{
+            "bids":[["100.1","2.5"],["100.0","1.0"]],
+            "asks":[["100.2","3.0"],["100.3","0.5"]],
+            "ts":1,
+            "checksum":-868527451
+        }}"#;
+
+        match Message::from_text(text).expect("checksum should match") {
+            Message::OrderBook { bids, asks, .. } => {
+                assert_eq!(bids, vec![level("100.1", "2.5"), level("100.0", "1.0")]);
+                assert_eq!(asks, vec![level("100.2", "3.0"), level("100.3", "0.5")]);
+            }
+            _ => panic!("expected an OrderBook message"),
+        }
+    }
+
+    #[test]
+    fn from_text_rejects_a_tampered_order_book_frame() {
+        let text = r#"{"action":"order_book","message":{
+            "bids":[["100.1","2.5"],["100.0","1.0"]],
+            "asks":[["100.2","3.0"],["100.3","0.5"]],
+            "ts":1,
+            "checksum":0
+        }}"#;
+
+        match Message::from_text(text).expect_err("checksum should not match") {
+            ParseError::ChecksumMismatch { expected, actual } => {
+                assert_eq!(expected, 0);
+                assert_eq!(actual, -868527451);
+            }
+            other => panic!("expected a ChecksumMismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn from_text_decodes_a_trade_frame() {
+        let text = r#"{"action":"trade","message":{"price":"100.5","size":2.0,"side":"buy","ts":1}}"#;
+
+        match Message::from_text(text).expect("valid trade") {
+            Message::Trade {
+                price,
+                size,
+                side,
+                ts,
+            } => {
+                assert_eq!(price.to_string(), "100.5");
+                assert_eq!(size, 2.0);
+                assert_eq!(side, Side::Buy);
+                assert_eq!(ts, 1);
+            }
+            _ => panic!("expected a Trade message"),
+        }
+    }
+
+    #[test]
+    fn from_text_decodes_a_bbo_frame() {
+        let text = r#"{"action":"bbo","message":{"bid":["100.0","1.0"],"ask":["100.1","2.0"],"ts":1}}"#;
 
-        match value["action"].as_str() {
-            Some("candles") => {
-                let candles = value["message"].as_array().unwrap().iter().map(|v| v.as_f64().unwrap()).collect();
-                Self::Candles(candles)
+        match Message::from_text(text).expect("valid bbo") {
+            Message::Bbo { bid, ask, .. } => {
+                assert_eq!(bid, level("100.0", "1.0"));
+                assert_eq!(ask, level("100.1", "2.0"));
             }
-            _ => Self::Unknown,
+            _ => panic!("expected a Bbo message"),
         }
     }
 }
@@ -0,0 +1,19 @@
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde_json::Value;
+
+/// A fixed-point price. `f64` accumulates rounding error over repeated
+/// arithmetic and can misrepresent exchange prices, so every price in this
+/// crate is a `Decimal`.
+pub type Price = Decimal;
+
+/// Parse a price from a JSON value that may arrive as either a string
+/// (preserving full precision) or a number (as some feeds send it).
+pub fn parse_price(value: &Value) -> Option<Price> {
+    match value {
+        Value::String(s) => Decimal::from_str(s).ok(),
+        Value::Number(n) => Decimal::from_str(&n.to_string()).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// A single instrument/candle feed that can be subscribed to over one socket.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Channel {
+    pub symbol: String,
+    pub timeframe: u32,
+}
+
+impl Channel {
+    pub fn new(symbol: impl Into<String>, timeframe: u32) -> Self {
+        Self {
+            symbol: symbol.into(),
+            timeframe,
+        }
+    }
+}
+
+/// A control frame asking the server to add or remove channels from the
+/// active subscription set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", content = "message", rename_all = "snake_case")]
+pub enum Command {
+    Subscribe(Vec<Channel>),
+    Unsubscribe(Vec<Channel>),
+}
+
+/// Tracks the channels a socket is currently subscribed to so a reconnect
+/// can replay them.
+#[derive(Debug, Default, Clone)]
+pub struct Subscription {
+    channels: HashSet<Channel>,
+}
+
+impl Subscription {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, channels: &[Channel]) {
+        self.channels.extend(channels.iter().cloned());
+    }
+
+    pub fn remove(&mut self, channels: &[Channel]) {
+        for channel in channels {
+            self.channels.remove(channel);
+        }
+    }
+
+    /// The currently active channels, in no particular order.
+    pub fn active(&self) -> Vec<Channel> {
+        self.channels.iter().cloned().collect()
+    }
+}
+
+/// Build a `{"action":"subscribe"|"unsubscribe","message":[...]}` frame.
+pub fn to_frame(action: &str, channels: &[Channel]) -> String {
+    json!({
+        "action": action,
+        "message": channels,
+    })
+    .to_string()
+}
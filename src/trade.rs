@@ -1,20 +1,22 @@
 use serde_json::json;
 
+use crate::price::Price;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Trade {
     pub direction: String,
-    pub price: f64,
+    pub price: Price,
 }
 
 impl Trade {
-    pub fn call(price: f64) -> Self {
+    pub fn call(price: Price) -> Self {
         Self {
             direction: "call".to_string(),
             price,
         }
     }
 
-    pub fn put(price: f64) -> Self {
+    pub fn put(price: Price) -> Self {
         Self {
             direction: "put".to_string(),
             price,
@@ -25,7 +27,7 @@ impl Trade {
         json!({
             "action": "trade",
             "direction": self.direction,
-            "price": self.price,
+            "price": self.price.to_string(),
         })
         .to_string()
     }
@@ -1,14 +1,58 @@
-use std::ops::ControlFlow;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use futures_util::stream::{unfold, Stream, StreamExt};
+use futures_util::SinkExt;
 use rand::Rng;
 use regex::Regex;
 use reqwest;
 use serde_json::{json, Value};
-use websocket::header::{Headers, Origin};
-use websocket::sync::Client;
-use websocket::ClientBuilder;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 
-/// Establishes a WebSocket connection to TradingView and starts a job to receive quotes for a given pair and market.
+use crate::price::{self, Price};
+
+type TvSocket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Errors that can occur while streaming quotes from TradingView.
+#[derive(Debug)]
+pub enum TradingViewError {
+    /// The WebSocket connection failed or dropped.
+    WebSocket(tokio_tungstenite::tungstenite::Error),
+    /// The symbol search request failed.
+    Http(reqwest::Error),
+    /// A quote frame didn't decode into a `Quote`.
+    InvalidQuote(String),
+}
+
+impl fmt::Display for TradingViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WebSocket(err) => write!(f, "websocket error: {}", err),
+            Self::Http(err) => write!(f, "http error: {}", err),
+            Self::InvalidQuote(text) => write!(f, "invalid quote frame: {}", text),
+        }
+    }
+}
+
+impl std::error::Error for TradingViewError {}
+
+/// A single last-price update for a symbol, as streamed from TradingView's
+/// `quote_*` session protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub symbol: String,
+    pub lp: Option<Price>,
+    pub volume: Option<f64>,
+    pub ch: Option<f64>,
+    pub chp: Option<f64>,
+    /// Unix timestamp in milliseconds, taken when the quote was received.
+    pub timestamp: u128,
+}
+
+/// Opens a TradingView quote session and returns a stream of `Quote` updates
+/// for a given pair and market, preserving the `~m~<len>~m~` framing and
+/// session handshake used by the browser client.
 ///
 /// # Arguments
 ///
@@ -18,62 +62,163 @@ use websocket::ClientBuilder;
 /// # Example
 ///
 /// ```
-/// tradingview_ws("btcusdt", "crypto");
+/// quote_stream("btcusdt", "crypto").await;
 /// ```
-///
-/// # Panics
-///
-/// The function panics if the WebSocket connection cannot be established.
-pub fn tradingview_ws(pair: &str, market: &str) {
+pub async fn quote_stream(
+    pair: &str,
+    market: &str,
+) -> Result<impl Stream<Item = Result<Quote, TradingViewError>>, TradingViewError> {
     let trading_view_socket = "wss://data.tradingview.com/socket.io/websocket";
 
-    let mut headers = Headers::new();
-    headers.set(Origin("https://data.tradingview.com".to_string()));
-
-    let mut client = ClientBuilder::new(trading_view_socket)
-        .unwrap()
-        .custom_headers(&headers)
-        .connect_insecure()
-        .unwrap();
+    let (mut ws, _) = connect_async(trading_view_socket)
+        .await
+        .map_err(TradingViewError::WebSocket)?;
 
     let session = generate_session();
+    let symbol_id = get_symbol_id(pair, market).await?;
 
     send_message(
-        &mut client,
+        &mut ws,
         "quote_create_session",
         vec![json!(session.clone())],
-    );
+    )
+    .await?;
     send_message(
-        &mut client,
+        &mut ws,
         "quote_set_fields",
         vec![
             json!(session.clone()),
-            serde_json::Value::String("lp".to_string()),
-            serde_json::Value::String("volume".to_string()),
-            serde_json::Value::String("ch".to_string()),
-            serde_json::Value::String("chp".to_string()),
+            Value::String("lp".to_string()),
+            Value::String("volume".to_string()),
+            Value::String("ch".to_string()),
+            Value::String("chp".to_string()),
         ],
-    );
+    )
+    .await?;
     send_message(
-        &mut client,
+        &mut ws,
         "quote_add_symbols",
-        vec![json!(session), json!(get_symbol_id(pair, market))],
-    );
+        vec![json!(session), json!(symbol_id)],
+    )
+    .await?;
+
+    Ok(unfold(ws, |mut ws| async move {
+        loop {
+            match ws.next().await {
+                Some(Ok(WsMessage::Text(text))) => match handle_frame(&mut ws, &text).await {
+                    Some(result) => return Some((result, ws)),
+                    None => continue,
+                },
+                Some(Ok(WsMessage::Ping(payload))) => {
+                    let _ = ws.send(WsMessage::Pong(payload)).await;
+                }
+                Some(Ok(WsMessage::Close(_))) | None => return None,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Some((Err(TradingViewError::WebSocket(e)), ws)),
+            }
+        }
+    }))
+}
+
+/// Handles one `~m~<len>~m~<content>` frame: replies to session heartbeats
+/// and decodes quote payloads, returning `None` for anything that yields no
+/// quote.
+///
+/// # Arguments
+///
+/// * `ws` - A mutable reference to the WebSocket connection.
+/// * `frame` - A string slice containing the frame content to parse.
+async fn handle_frame(ws: &mut TvSocket, frame: &str) -> Option<Result<Quote, TradingViewError>> {
+    if frame.contains("quote_completed") || frame.contains("session_id") {
+        return None;
+    }
+
+    let regex_ = Regex::new(r"^.*?(\{.*\})$").unwrap();
+    let json_str = regex_
+        .captures(frame)
+        .and_then(|cap| cap.get(1).map(|m| m.as_str()));
+
+    match json_str {
+        Some(json_str) => Some(parse_quote(json_str)),
+        None => {
+            if let Some(reply) = ping_reply(frame) {
+                let _ = ws.send(reply).await;
+            }
+            None
+        }
+    }
+}
+
+/// Parses the `{"m":"qsd","p":[session,{"n":symbol,"v":{...}}]}` payload
+/// TradingView sends for each quote update.
+///
+/// # Arguments
+///
+/// * `json_str` - A string slice that holds the JSON data.
+fn parse_quote(json_str: &str) -> Result<Quote, TradingViewError> {
+    let value: Value = serde_json::from_str(json_str)
+        .map_err(|e| TradingViewError::InvalidQuote(e.to_string()))?;
+
+    let prefix = value["p"][1]
+        .as_object()
+        .ok_or_else(|| TradingViewError::InvalidQuote(json_str.to_string()))?;
 
-    socket_job(&mut client);
+    let symbol = prefix["n"]
+        .as_str()
+        .ok_or_else(|| TradingViewError::InvalidQuote(json_str.to_string()))?
+        .to_string();
+
+    let values = &prefix["v"];
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    Ok(Quote {
+        symbol,
+        lp: price::parse_price(&values["lp"]),
+        volume: values["volume"].as_f64(),
+        ch: values["ch"].as_f64(),
+        chp: values["chp"].as_f64(),
+        timestamp,
+    })
+}
+
+/// Rebuilds a heartbeat reply for a non-JSON `~m~<len>~m~~h~<id>` frame.
+///
+/// # Arguments
+///
+/// * `frame` - A string slice containing the frame to derive the reply from.
+fn ping_reply(frame: &str) -> Option<WsMessage> {
+    let ping_str = Regex::new(r".......(.*)")
+        .unwrap()
+        .captures(frame)
+        .and_then(|cap| cap.get(1).map(|m| m.as_str()))
+        .unwrap_or("");
+
+    if ping_str.is_empty() {
+        None
+    } else {
+        Some(WsMessage::Text(prepend_header(ping_str)))
+    }
 }
 
 /// Send a message over the WebSocket connection
 ///
 /// # Arguments
 ///
-/// * `ws` - A mutable reference to a `Client` object that holds the WebSocket connection.
+/// * `ws` - A mutable reference to the WebSocket connection.
 /// * `func` - A string slice that holds the name of the function.
 /// * `args` - A vector of `Value` objects that holds the parameters of the function.
-pub fn send_message(ws: &mut Client<std::net::TcpStream>, func: &str, args: Vec<Value>) {
+async fn send_message(
+    ws: &mut TvSocket,
+    func: &str,
+    args: Vec<Value>,
+) -> Result<(), TradingViewError> {
     let message = create_message(func, args);
-    ws.send_message(&websocket::message::OwnedMessage::Text(message))
-        .unwrap();
+    ws.send(WsMessage::Text(message))
+        .await
+        .map_err(TradingViewError::WebSocket)
 }
 
 /// Create a full message with header
@@ -129,16 +274,21 @@ fn prepend_header(content: &str) -> String {
 /// # Returns
 ///
 /// A string that represents the symbol ID
-pub fn get_symbol_id(pair: &str, market: &str) -> String {
-    let data = search(pair, market).unwrap();
-    let symbol_name = data["symbol"].as_str().unwrap();
+async fn get_symbol_id(pair: &str, market: &str) -> Result<String, TradingViewError> {
+    let data = search(pair, market).await?;
+    let symbol_name = data["symbol"]
+        .as_str()
+        .ok_or_else(|| TradingViewError::InvalidQuote(data.to_string()))?;
     let broker = data
         .get("prefix")
         .and_then(|prefix| prefix.as_str())
-        .unwrap_or_else(|| data["exchange"].as_str().unwrap());
-    let symbol_id = format!("{}:{}", broker.to_uppercase(), symbol_name.to_uppercase());
-    println!("{}", symbol_id);
-    symbol_id
+        .or_else(|| data["exchange"].as_str())
+        .ok_or_else(|| TradingViewError::InvalidQuote(data.to_string()))?;
+    Ok(format!(
+        "{}:{}",
+        broker.to_uppercase(),
+        symbol_name.to_uppercase()
+    ))
 }
 
 /// Search for a symbol based on query and category
@@ -150,20 +300,24 @@ pub fn get_symbol_id(pair: &str, market: &str) -> String {
 ///
 /// # Returns
 ///
-/// A `Result` containing a `Value` object if the search is successful, or a `reqwest::Error` if the search fails.
-pub fn search(query: &str, category: &str) -> Result<Value, reqwest::Error> {
+/// A `Result` containing a `Value` object if the search is successful, or a `TradingViewError` if the search fails.
+async fn search(query: &str, category: &str) -> Result<Value, TradingViewError> {
     let url = format!(
         "https://symbol-search.tradingview.com/symbol_search/?text={}&type={}",
         query, category
     );
-    let response = reqwest::blocking::get(&url)?;
-    if response.status().is_success() {
-        let data: Value = response.json()?;
-        assert!(!data.is_null(), "Nothing Found.");
-        Ok(data[0].clone())
-    } else {
-        Err(response.error_for_status().unwrap_err())
-    }
+    let response = reqwest::get(&url).await.map_err(TradingViewError::Http)?;
+    let data: Value = response
+        .error_for_status()
+        .map_err(TradingViewError::Http)?
+        .json()
+        .await
+        .map_err(TradingViewError::Http)?;
+
+    data.as_array()
+        .and_then(|arr| arr.first())
+        .cloned()
+        .ok_or_else(|| TradingViewError::InvalidQuote("empty symbol search result".to_string()))
 }
 
 /// Generate a random session ID
@@ -171,7 +325,7 @@ pub fn search(query: &str, category: &str) -> Result<Value, reqwest::Error> {
 /// # Returns
 ///
 /// A `String` containing a random session ID.
-pub fn generate_session() -> String {
+fn generate_session() -> String {
     let string_length = 12;
     let letters = "abcdefghijklmnopqrstuvwxyz";
     let mut rng = rand::thread_rng();
@@ -181,115 +335,3 @@ pub fn generate_session() -> String {
         .collect();
     format!("qs_{}", random_string)
 }
-
-/// Sends a ping packet to the WebSocket server.
-///
-/// # Arguments
-///
-/// * `ws` - A mutable reference to the WebSocket client.
-/// * `result` - A string slice containing the result to parse.
-///
-/// # Example
-///
-/// ```
-/// let mut ws = Client::<TcpStream>::connect(url).unwrap();
-/// let result = ws.recv_message().unwrap().into_text().unwrap();
-/// send_ping_packet(&mut ws, &result);
-/// ```
-fn send_ping_packet(ws: &mut Client<std::net::TcpStream>, result: &str) {
-    let ping_str = Regex::new(r".......(.*)")
-        .unwrap()
-        .captures(result)
-        .and_then(|cap| cap.get(1).map(|m| m.as_str()))
-        .unwrap_or("");
-
-    if !ping_str.is_empty() {
-        let ping_message = format!("~m~{}~m~{}", ping_str.len(), ping_str);
-        ws.send_message(&websocket::message::OwnedMessage::Text(ping_message))
-            .unwrap();
-    }
-}
-
-/// Handles WebSocket messages received by the client.
-///
-/// # Arguments
-///
-/// * `ws` - A mutable reference to the WebSocket client.
-pub fn socket_job(ws: &mut Client<std::net::TcpStream>) {
-    let regex_ = Regex::new(r"^.*?({.*)}$").unwrap();
-
-    loop {
-        let result = ws.recv_message().unwrap();
-        match result {
-            websocket::message::OwnedMessage::Text(ref result) => {
-                if let ControlFlow::Break(_) = get_price(result, &regex_, ws) {
-                    continue;
-                }
-            }
-            _ => {}
-        }
-    }
-}
-
-/// Parses the `result` string using the provided `regex_` and extracts the price, volume, change, and change percentage
-/// information for a given symbol. If the `result` string contains "quote_completed" or "session_id", the function
-/// returns `ControlFlow::Break(())` to stop the loop. Otherwise, the function prints the extracted information to the console
-/// and returns `ControlFlow::Continue(())`.
-///
-/// # Arguments
-///
-/// * `result` - A reference to a `String` containing the result string to parse.
-/// * `regex_` - A reference to a `Regex` object used to extract the JSON string from the `result` string.
-/// * `ws` - A mutable reference to a `Client` object used to send a ping packet if the `result` string cannot be parsed.
-///
-/// # Returns
-///
-/// * `ControlFlow::Break(())` if the `result` string contains "quote_completed" or "session_id".
-/// * `ControlFlow::Continue(())` otherwise.
-fn get_price(
-    result: &String,
-    regex_: &Regex,
-    ws: &mut Client<std::net::TcpStream>,
-) -> ControlFlow<()> {
-    if result.contains("quote_completed") || result.contains("session_id") {
-        return ControlFlow::Break(());
-    }
-
-    let res = regex_
-        .captures(result)
-        .and_then(|cap| cap.get(1).map(|m| m.as_str()));
-
-    if let Some(json_str) = res {
-        parse_price_data(json_str);
-    } else {
-        send_ping_packet(ws, result);
-    }
-
-    ControlFlow::Continue(())
-}
-
-/// Parses the price data from a JSON string and prints the symbol, price, change, change percentage, and volume.
-///
-/// # Arguments
-///
-/// * `json_str` - A string slice that holds the JSON data.
-///
-fn parse_price_data(json_str: &str) {
-    let json_res: Value = serde_json::from_str(json_str).unwrap();
-    if let Some(prefix) = json_res["p"][1].as_object() {
-        let symbol = prefix["n"].as_str().unwrap();
-        let price = prefix["v"]["lp"].as_f64();
-        let volume = prefix["v"]["volume"].as_f64();
-        let change = prefix["v"]["ch"].as_f64();
-        let change_percentage = prefix["v"]["chp"].as_f64();
-
-        print!(
-            "{}, price={}, change={}, change_percentage={}, volume={}\n",
-            symbol,
-            price.unwrap_or(0.0),
-            change.unwrap_or(0.0),
-            change_percentage.unwrap_or(0.0),
-            volume.unwrap_or(0.0)
-        );
-    }
-}